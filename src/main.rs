@@ -1,20 +1,99 @@
 use axum::{
-    extract::Json,
+    extract::{Json, State},
     http::StatusCode,
     response::{Html, IntoResponse},
     routing::{get, post},
     Router,
 };
+use futures::future::join_all;
+use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
 use tracing::{info, warn};
 
+mod metrics;
+mod options;
+mod registry;
+mod tls;
+use metrics::Metrics;
+use options::RawOptions;
+use registry::FormatterRegistry;
+
+/// Default deadline for a single formatter invocation, in seconds; overridable
+/// via `--format-timeout-secs` or the `FORMAT_TIMEOUT_SECS` env var.
+const DEFAULT_FORMAT_TIMEOUT_SECS: u64 = 10;
+/// Default number of formatter processes allowed to run concurrently;
+/// overridable via `--max-concurrent-formatters` or `MAX_CONCURRENT_FORMATTERS`.
+const DEFAULT_MAX_CONCURRENT_FORMATTERS: usize = 16;
+/// How long a request waits for a concurrency permit before being rejected as busy.
+const PERMIT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(2);
+const SERVICE_VERSION: &str = "0.1.0";
+const INDEX_TEMPLATE_PATH: &str = "templates/index.html.hbs";
+
+/// Runtime tunables read from CLI flags / env vars at startup, mirroring how
+/// `tls::parse_settings` resolves its own flags.
+struct RuntimeSettings {
+    format_timeout: Duration,
+    max_concurrent_formatters: usize,
+}
+
+/// Parse `--format-timeout-secs`/`--max-concurrent-formatters` CLI flags,
+/// falling back to the `FORMAT_TIMEOUT_SECS`/`MAX_CONCURRENT_FORMATTERS` env
+/// vars, then the defaults above. Unparsable values are ignored in favor of
+/// whatever was already resolved.
+fn parse_runtime_settings() -> RuntimeSettings {
+    let mut format_timeout_secs = std::env::var("FORMAT_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_FORMAT_TIMEOUT_SECS);
+    let mut max_concurrent_formatters = std::env::var("MAX_CONCURRENT_FORMATTERS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_FORMATTERS);
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format-timeout-secs" => {
+                if let Some(value) = args.next().and_then(|v| v.parse().ok()) {
+                    format_timeout_secs = value;
+                }
+            }
+            "--max-concurrent-formatters" => {
+                if let Some(value) = args.next().and_then(|v| v.parse().ok()) {
+                    max_concurrent_formatters = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    RuntimeSettings {
+        format_timeout: Duration::from_secs(format_timeout_secs),
+        max_concurrent_formatters,
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    metrics: Arc<Metrics>,
+    formatter_semaphore: Arc<Semaphore>,
+    format_timeout: Duration,
+    registry: Arc<FormatterRegistry>,
+    templates: Arc<Handlebars<'static>>,
+}
+
 #[derive(Debug, Deserialize)]
 struct FormatRequest {
     code: String,
     language: String,
     formatter: Option<String>,
+    #[serde(default)]
+    options: RawOptions,
 }
 
 #[derive(Debug, Serialize)]
@@ -31,138 +110,447 @@ struct ErrorResponse {
     details: String,
 }
 
-async fn health() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "status": "healthy",
-        "service": "speed-formatter-mvp",
-        "version": "0.1.0"
-    }))
+#[derive(Debug, Deserialize)]
+struct BatchFormatItem {
+    code: String,
+    language: String,
+    formatter: Option<String>,
+    filename: Option<String>,
+    #[serde(default)]
+    options: RawOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItemResult {
+    filename: Option<String>,
+    formatted_code: Option<String>,
+    execution_time_ms: u128,
+    formatter_used: Option<String>,
+    status: String,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchSummary {
+    total: usize,
+    succeeded: usize,
+    failed: usize,
+    total_execution_time_ms: u128,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchFormatResponse {
+    results: Vec<BatchItemResult>,
+    summary: BatchSummary,
 }
 
-async fn format_code(Json(payload): Json<FormatRequest>) -> impl IntoResponse {
+/// The outcome of running one formatting job, independent of how it's surfaced
+/// (a single HTTP response vs. one entry in a batch response).
+struct FormatOutcome {
+    status: &'static str,
+    formatted_code: Option<String>,
+    formatter_used: Option<String>,
+    execution_time_ms: u128,
+    error_title: Option<&'static str>,
+    error_details: Option<String>,
+}
+
+/// Run one formatting job end to end: resolve the formatter, validate
+/// options, acquire a concurrency permit, and invoke the formatter under the
+/// shared timeout. Shared by the single-item and batch endpoints.
+async fn execute_format(
+    state: &AppState,
+    language: &str,
+    formatter_name: Option<&str>,
+    code: &str,
+    raw_options: &RawOptions,
+) -> FormatOutcome {
     let start_time = std::time::Instant::now();
-    
-    info!("Formatting {} code with {} characters", payload.language, payload.code.len());
-    
-    let result = match payload.language.as_str() {
-        "javascript" | "typescript" | "js" | "ts" => {
-            format_with_prettier(&payload.code).await
-        },
-        "rust" => {
-            format_with_rustfmt(&payload.code).await
-        },
+    let _in_flight = state.metrics.in_flight_guard();
+
+    let spec = match state.registry.find(language, formatter_name) {
+        Some(spec) => spec.clone(),
+        None => {
+            let execution_time_ms = start_time.elapsed().as_millis();
+            // `language`/`formatter_name` are unvalidated client input here (no
+            // registry match yet), so record under fixed labels rather than the
+            // raw values — otherwise an unauthenticated caller could grow the
+            // metrics maps without bound just by varying either field per request.
+            state
+                .metrics
+                .record_request("unknown", "unknown", "error", execution_time_ms);
+            let (error_title, details) = match formatter_name {
+                Some(formatter) if !state.registry.is_known_formatter(formatter) => (
+                    "Unknown formatter",
+                    format!("Formatter '{}' is not registered", formatter),
+                ),
+                Some(formatter) => (
+                    "Unsupported language",
+                    format!("Formatter '{}' does not support language '{}'", formatter, language),
+                ),
+                None => (
+                    "Unsupported language",
+                    format!("Language '{}' is not supported yet", language),
+                ),
+            };
+            return FormatOutcome {
+                status: "bad_request",
+                formatted_code: None,
+                formatter_used: None,
+                execution_time_ms,
+                error_title: Some(error_title),
+                error_details: Some(details),
+            };
+        }
+    };
+
+    let format_options = match options::validate(raw_options) {
+        Ok(options) => options,
+        Err(rejected) => {
+            let execution_time_ms = start_time.elapsed().as_millis();
+            state
+                .metrics
+                .record_request(language, &spec.name, "error", execution_time_ms);
+            return FormatOutcome {
+                status: "bad_request",
+                formatted_code: None,
+                formatter_used: Some(spec.name),
+                execution_time_ms,
+                error_title: Some("Invalid formatting options"),
+                error_details: Some(rejected.join("; ")),
+            };
+        }
+    };
+    let extra_args = options::translate_for_formatter(spec.option_style, &format_options);
+
+    let permit = match tokio::time::timeout(
+        PERMIT_ACQUIRE_TIMEOUT,
+        state.formatter_semaphore.clone().acquire_owned(),
+    )
+    .await
+    {
+        Ok(Ok(permit)) => permit,
         _ => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Unsupported language".to_string(),
-                    details: format!("Language '{}' is not supported yet", payload.language),
-                })
-            ).into_response();
+            warn!("Rejecting request: no formatter concurrency permit available");
+            let execution_time_ms = start_time.elapsed().as_millis();
+            state
+                .metrics
+                .record_request(language, "none", "busy", execution_time_ms);
+            return FormatOutcome {
+                status: "busy",
+                formatted_code: None,
+                formatter_used: Some(spec.name),
+                execution_time_ms,
+                error_title: Some("Server busy"),
+                error_details: Some(
+                    "too many concurrent format requests, try again shortly".to_string(),
+                ),
+            };
         }
     };
-    
-    let execution_time = start_time.elapsed().as_millis();
-    
-    match result {
-        Ok((formatted, formatter)) => {
-            info!("Successfully formatted in {}ms using {}", execution_time, formatter);
-            Json(FormatResponse {
-                formatted_code: formatted,
-                execution_time_ms: execution_time,
-                formatter_used: formatter,
-                status: "success".to_string(),
-            }).into_response()
-        },
-        Err(error) => {
+
+    let timed_result = tokio::time::timeout(
+        state.format_timeout,
+        registry::run_formatter(&spec, code, &extra_args),
+    )
+    .await;
+    drop(permit);
+
+    let execution_time_ms = start_time.elapsed().as_millis();
+    let formatter = spec.name.clone();
+
+    match timed_result {
+        Ok(Ok(formatted)) => {
+            info!("Successfully formatted in {}ms using {}", execution_time_ms, formatter);
+            state
+                .metrics
+                .record_request(language, &formatter, "success", execution_time_ms);
+            FormatOutcome {
+                status: "success",
+                formatted_code: Some(formatted),
+                formatter_used: Some(formatter),
+                execution_time_ms,
+                error_title: None,
+                error_details: None,
+            }
+        }
+        Ok(Err(error)) => {
             warn!("Formatting failed: {}", error);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Formatting failed".to_string(),
-                    details: error,
-                })
-            ).into_response()
+            state
+                .metrics
+                .record_request(language, &formatter, "error", execution_time_ms);
+            FormatOutcome {
+                status: "error",
+                formatted_code: None,
+                formatter_used: Some(formatter),
+                execution_time_ms,
+                error_title: Some("Formatting failed"),
+                error_details: Some(error),
+            }
+        }
+        Err(_elapsed) => {
+            warn!("Formatting timed out after {:?}", state.format_timeout);
+            state
+                .metrics
+                .record_request(language, &formatter, "timeout", execution_time_ms);
+            FormatOutcome {
+                status: "timeout",
+                formatted_code: None,
+                formatter_used: Some(formatter),
+                execution_time_ms,
+                error_title: Some("Formatting timed out"),
+                error_details: Some(format!(
+                    "formatter timed out after {}s",
+                    state.format_timeout.as_secs()
+                )),
+            }
         }
     }
 }
 
-async fn format_with_prettier(code: &str) -> Result<(String, String), String> {
-    // First try to use Prettier if available
-    let mut cmd = Command::new("npx");
-    cmd.args(&["prettier", "--stdin-filepath", "file.js", "--parser", "babel"]);
-    
-    let output = cmd
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn prettier: {}", e))?;
-    
-    let stdin = output.stdin.as_ref().ok_or("Failed to open stdin")?;
-    std::io::Write::write_all(&mut std::io::BufWriter::new(stdin), code.as_bytes())
-        .map_err(|e| format!("Failed to write to stdin: {}", e))?;
-    
-    let result = output.wait_with_output().map_err(|e| format!("Prettier failed: {}", e))?;
-    
-    if result.status.success() {
-        let formatted = String::from_utf8_lossy(&result.stdout).to_string();
-        Ok((formatted, "prettier".to_string()))
-    } else {
-        let error = String::from_utf8_lossy(&result.stderr);
-        Err(format!("Prettier error: {}", error))
-    }
+async fn health() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "healthy",
+        "service": "speed-formatter-mvp",
+        "version": SERVICE_VERSION
+    }))
 }
 
-async fn format_with_rustfmt(code: &str) -> Result<(String, String), String> {
-    let mut cmd = Command::new("rustfmt");
-    cmd.arg("--emit").arg("stdout");
-    
-    let output = cmd
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn rustfmt: {}", e))?;
-    
-    let stdin = output.stdin.as_ref().ok_or("Failed to open stdin")?;
-    std::io::Write::write_all(&mut std::io::BufWriter::new(stdin), code.as_bytes())
-        .map_err(|e| format!("Failed to write to stdin: {}", e))?;
-    
-    let result = output.wait_with_output().map_err(|e| format!("rustfmt failed: {}", e))?;
-    
-    if result.status.success() {
-        let formatted = String::from_utf8_lossy(&result.stdout).to_string();
-        Ok((formatted, "rustfmt".to_string()))
-    } else {
-        let error = String::from_utf8_lossy(&result.stderr);
-        Err(format!("rustfmt error: {}", error))
+async fn format_code(
+    State(state): State<AppState>,
+    Json(payload): Json<FormatRequest>,
+) -> impl IntoResponse {
+    info!("Formatting {} code with {} characters", payload.language, payload.code.len());
+
+    let outcome = execute_format(
+        &state,
+        &payload.language,
+        payload.formatter.as_deref(),
+        &payload.code,
+        &payload.options,
+    )
+    .await;
+
+    match outcome.status {
+        "success" => Json(FormatResponse {
+            formatted_code: outcome.formatted_code.unwrap_or_default(),
+            execution_time_ms: outcome.execution_time_ms,
+            formatter_used: outcome.formatter_used.unwrap_or_default(),
+            status: "success".to_string(),
+        })
+        .into_response(),
+        "busy" => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: outcome.error_title.unwrap_or_default().to_string(),
+                details: outcome.error_details.unwrap_or_default(),
+            }),
+        )
+            .into_response(),
+        "timeout" => (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(ErrorResponse {
+                error: outcome.error_title.unwrap_or_default().to_string(),
+                details: outcome.error_details.unwrap_or_default(),
+            }),
+        )
+            .into_response(),
+        "bad_request" => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: outcome.error_title.unwrap_or_default().to_string(),
+                details: outcome.error_details.unwrap_or_default(),
+            }),
+        )
+            .into_response(),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: outcome.error_title.unwrap_or_default().to_string(),
+                details: outcome.error_details.unwrap_or_default(),
+            }),
+        )
+            .into_response(),
     }
 }
 
-async fn serve_ui() -> Html<&'static str> {
-    Html(include_str!("../static/index.html"))
+async fn format_batch(
+    State(state): State<AppState>,
+    Json(items): Json<Vec<BatchFormatItem>>,
+) -> impl IntoResponse {
+    info!("Batch formatting {} item(s)", items.len());
+
+    let jobs = items.iter().map(|item| {
+        let state = &state;
+        async move {
+            let outcome = execute_format(
+                state,
+                &item.language,
+                item.formatter.as_deref(),
+                &item.code,
+                &item.options,
+            )
+            .await;
+            BatchItemResult {
+                filename: item.filename.clone(),
+                formatted_code: outcome.formatted_code,
+                execution_time_ms: outcome.execution_time_ms,
+                formatter_used: outcome.formatter_used,
+                status: outcome.status.to_string(),
+                error: outcome.error_details,
+            }
+        }
+    });
+
+    let results = join_all(jobs).await;
+
+    let summary = BatchSummary {
+        total: results.len(),
+        succeeded: results.iter().filter(|r| r.status == "success").count(),
+        failed: results.iter().filter(|r| r.status != "success").count(),
+        total_execution_time_ms: results.iter().map(|r| r.execution_time_ms).sum(),
+    };
+
+    Json(BatchFormatResponse { results, summary }).into_response()
+}
+
+async fn serve_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [("content-type", "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+async fn serve_ui(State(state): State<AppState>) -> impl IntoResponse {
+    let formatters: Vec<_> = state
+        .registry
+        .all()
+        .iter()
+        .map(|f| {
+            serde_json::json!({
+                "name": f.name,
+                "languages": f.languages,
+            })
+        })
+        .collect();
+
+    let mut languages: Vec<String> = state
+        .registry
+        .all()
+        .iter()
+        .flat_map(|f| f.languages.clone())
+        .collect();
+    languages.sort();
+    languages.dedup();
+
+    let context = serde_json::json!({
+        "version": SERVICE_VERSION,
+        "languages": languages,
+        "formatters": formatters,
+        "format_timeout_secs": state.format_timeout.as_secs(),
+    });
+
+    match state.templates.render("index", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            warn!("Failed to render index template: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "template rendering failed").into_response()
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::init();
-    
+
+    let mut handlebars = Handlebars::new();
+    handlebars
+        .register_template_file("index", INDEX_TEMPLATE_PATH)
+        .expect("Failed to register index template");
+
+    let runtime_settings = parse_runtime_settings();
+
+    let state = AppState {
+        metrics: Arc::new(Metrics::new()),
+        formatter_semaphore: Arc::new(Semaphore::new(runtime_settings.max_concurrent_formatters)),
+        format_timeout: runtime_settings.format_timeout,
+        registry: Arc::new(FormatterRegistry::load(registry::DEFAULT_CONFIG_PATH)),
+        templates: Arc::new(handlebars),
+    };
+
     let app = Router::new()
         .route("/", get(serve_ui))
         .route("/health", get(health))
         .route("/format", post(format_code))
-        .layer(CorsLayer::permissive());
-    
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
-        .await
-        .expect("Failed to bind to port 3000");
-        
-    info!("ðŸš€ Speed Formatter MVP running on http://0.0.0.0:3000");
-    info!("ðŸ“Š Health check: http://localhost:3000/health");
-    info!("ðŸŽ¨ Format API: POST http://localhost:3000/format");
-    
-    axum::serve(listener, app)
+        .route("/format/batch", post(format_batch))
+        .route("/metrics", get(serve_metrics))
+        .layer(CompressionLayer::new())
+        .layer(CorsLayer::permissive())
+        .with_state(state);
+
+    let settings = tls::parse_settings();
+    let listener = tokio::net::TcpListener::bind(&settings.listen_addr)
         .await
-        .expect("Server failed to start");
+        .expect("Failed to bind listener");
+
+    let scheme = if settings.tls.is_some() { "https" } else { "http" };
+    info!("ðŸš€ Speed Formatter MVP running on {}://{}", scheme, settings.listen_addr);
+    info!("ðŸ“Š Health check: {}://{}/health", scheme, settings.listen_addr);
+    info!("ðŸŽ¨ Format API: POST {}://{}/format", scheme, settings.listen_addr);
+    info!("ðŸ“¦ Batch format API: POST {}://{}/format/batch", scheme, settings.listen_addr);
+    info!("ðŸ“ˆ Metrics: {}://{}/metrics", scheme, settings.listen_addr);
+
+    match settings.tls {
+        Some(tls_files) => {
+            let acceptor = tls::load_acceptor(&tls_files).expect("Failed to load TLS cert/key");
+            info!("TLS enabled");
+            serve_with_tls(listener, app, acceptor).await;
+        }
+        None => {
+            axum::serve(listener, app)
+                .await
+                .expect("Server failed to start");
+        }
+    }
+}
+
+/// Accept connections and terminate TLS on each before handing it to the axum
+/// app, since `axum::serve` only speaks plaintext HTTP.
+async fn serve_with_tls(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    acceptor: tokio_rustls::TlsAcceptor,
+) {
+    loop {
+        let (stream, _addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+
+        tokio::spawn(async move {
+            let tls_stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("TLS handshake failed: {}", e);
+                    return;
+                }
+            };
+
+            let io = hyper_util::rt::TokioIo::new(tls_stream);
+            let hyper_service =
+                hyper::service::service_fn(move |request| tower::Service::call(&mut app.clone(), request));
+
+            if let Err(e) = hyper_util::server::conn::auto::Builder::new(hyper_util::rt::TokioExecutor::new())
+                .serve_connection_with_upgrades(io, hyper_service)
+                .await
+            {
+                warn!("Error serving TLS connection: {}", e);
+            }
+        });
+    }
 }
\ No newline at end of file
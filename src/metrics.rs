@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Histogram bucket upper bounds, in milliseconds. `+Inf` is implicit.
+const DURATION_BUCKETS_MS: &[f64] = &[
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0,
+];
+
+#[derive(Default)]
+struct DurationHistogram {
+    /// One counter per bucket in `DURATION_BUCKETS_MS`, cumulative (le-style).
+    bucket_counts: Vec<u64>,
+    sum_ms: f64,
+    count: u64,
+}
+
+impl DurationHistogram {
+    fn observe(&mut self, value_ms: f64) {
+        if self.bucket_counts.is_empty() {
+            self.bucket_counts = vec![0; DURATION_BUCKETS_MS.len()];
+        }
+        for (bound, count) in DURATION_BUCKETS_MS.iter().zip(self.bucket_counts.iter_mut()) {
+            if value_ms <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum_ms += value_ms;
+        self.count += 1;
+    }
+}
+
+/// Process-wide Prometheus metrics for the formatting service.
+///
+/// Shared across handlers via `Arc` in axum state; all mutation goes through
+/// atomics or a short-held mutex so recording a sample never blocks a
+/// formatter invocation.
+#[derive(Default)]
+pub struct Metrics {
+    requests_total: Mutex<HashMap<(String, String, String), u64>>,
+    duration_histograms: Mutex<HashMap<(String, String), DurationHistogram>>,
+    in_flight: AtomicI64,
+    scrapes_total: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_request(&self, language: &str, formatter: &str, status: &str, duration_ms: u128) {
+        let key = (language.to_string(), formatter.to_string(), status.to_string());
+        *self
+            .requests_total
+            .lock()
+            .expect("metrics mutex poisoned")
+            .entry(key)
+            .or_insert(0) += 1;
+
+        self.duration_histograms
+            .lock()
+            .expect("metrics mutex poisoned")
+            .entry((language.to_string(), formatter.to_string()))
+            .or_default()
+            .observe(duration_ms as f64);
+    }
+
+    pub fn in_flight_guard(&self) -> InFlightGuard<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard { metrics: self }
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        self.scrapes_total.fetch_add(1, Ordering::Relaxed);
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP format_requests_total Total number of format requests.");
+        let _ = writeln!(out, "# TYPE format_requests_total counter");
+        let requests = self.requests_total.lock().expect("metrics mutex poisoned");
+        let mut requests: Vec<_> = requests.iter().collect();
+        requests.sort();
+        for ((language, formatter, status), count) in requests {
+            let (language, formatter, status) = (
+                escape_label(language),
+                escape_label(formatter),
+                escape_label(status),
+            );
+            let _ = writeln!(
+                out,
+                "format_requests_total{{language=\"{language}\",formatter=\"{formatter}\",status=\"{status}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(out, "# HELP format_duration_ms Duration of format requests in milliseconds.");
+        let _ = writeln!(out, "# TYPE format_duration_ms histogram");
+        let histograms = self
+            .duration_histograms
+            .lock()
+            .expect("metrics mutex poisoned");
+        let mut histograms: Vec<_> = histograms.iter().collect();
+        histograms.sort_by(|a, b| a.0.cmp(b.0));
+        for ((language, formatter), histogram) in histograms {
+            let (language, formatter) = (escape_label(language), escape_label(formatter));
+            for (bound, count) in DURATION_BUCKETS_MS.iter().zip(histogram.bucket_counts.iter()) {
+                let _ = writeln!(
+                    out,
+                    "format_duration_ms_bucket{{language=\"{language}\",formatter=\"{formatter}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "format_duration_ms_bucket{{language=\"{language}\",formatter=\"{formatter}\",le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "format_duration_ms_sum{{language=\"{language}\",formatter=\"{formatter}\"}} {}",
+                histogram.sum_ms
+            );
+            let _ = writeln!(
+                out,
+                "format_duration_ms_count{{language=\"{language}\",formatter=\"{formatter}\"}} {}",
+                histogram.count
+            );
+        }
+
+        let _ = writeln!(out, "# HELP format_requests_in_flight Number of format requests currently being processed.");
+        let _ = writeln!(out, "# TYPE format_requests_in_flight gauge");
+        let _ = writeln!(
+            out,
+            "format_requests_in_flight {}",
+            self.in_flight.load(Ordering::Relaxed)
+        );
+
+        out
+    }
+}
+
+/// Escape a label value per the Prometheus text exposition format so values
+/// sourced from client-controlled request fields (`language`, `formatter`)
+/// can't break the line they're written into or corrupt the rest of a scrape.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// RAII guard decrementing the in-flight gauge when a request finishes.
+pub struct InFlightGuard<'a> {
+    metrics: &'a Metrics,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
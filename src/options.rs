@@ -0,0 +1,101 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::registry::OptionStyle;
+
+/// Raw `options` map as received in a `FormatRequest`.
+pub type RawOptions = HashMap<String, Value>;
+
+/// Known formatter options, validated and range-checked.
+#[derive(Debug, Default, Clone)]
+pub struct FormatOptions {
+    pub tab_width: Option<u32>,
+    pub print_width: Option<u32>,
+    pub single_quote: Option<bool>,
+}
+
+const TAB_WIDTH_RANGE: std::ops::RangeInclusive<u64> = 1..=16;
+const PRINT_WIDTH_RANGE: std::ops::RangeInclusive<u64> = 1..=1000;
+
+/// Validate a raw options map, returning the rejected key/reason pairs if any
+/// key is unknown or out of range. Never silently forwards an unrecognized
+/// option to a formatter process.
+pub fn validate(raw: &RawOptions) -> Result<FormatOptions, Vec<String>> {
+    let mut options = FormatOptions::default();
+    let mut rejected = Vec::new();
+
+    for (key, value) in raw {
+        match key.as_str() {
+            "tab_width" => match value.as_u64() {
+                Some(n) if TAB_WIDTH_RANGE.contains(&n) => options.tab_width = Some(n as u32),
+                _ => rejected.push(format!(
+                    "tab_width: expected an integer in {}..={}, got {}",
+                    TAB_WIDTH_RANGE.start(),
+                    TAB_WIDTH_RANGE.end(),
+                    value
+                )),
+            },
+            "print_width" => match value.as_u64() {
+                Some(n) if PRINT_WIDTH_RANGE.contains(&n) => options.print_width = Some(n as u32),
+                _ => rejected.push(format!(
+                    "print_width: expected an integer in {}..={}, got {}",
+                    PRINT_WIDTH_RANGE.start(),
+                    PRINT_WIDTH_RANGE.end(),
+                    value
+                )),
+            },
+            "single_quote" => match value.as_bool() {
+                Some(b) => options.single_quote = Some(b),
+                None => rejected.push(format!("single_quote: expected a boolean, got {}", value)),
+            },
+            other => rejected.push(format!("{}: unknown option", other)),
+        }
+    }
+
+    if rejected.is_empty() {
+        Ok(options)
+    } else {
+        Err(rejected)
+    }
+}
+
+/// Translate validated options into extra CLI arguments for a formatter with
+/// the given [`OptionStyle`]. Options a style has no equivalent for (e.g.
+/// `single_quote` under `RustfmtConfig`) are silently dropped rather than
+/// rejected, since they were already validated as recognized options.
+pub fn translate_for_formatter(option_style: OptionStyle, options: &FormatOptions) -> Vec<String> {
+    match option_style {
+        OptionStyle::Prettier => {
+            let mut args = Vec::new();
+            if let Some(width) = options.tab_width {
+                args.push("--tab-width".to_string());
+                args.push(width.to_string());
+            }
+            if let Some(width) = options.print_width {
+                args.push("--print-width".to_string());
+                args.push(width.to_string());
+            }
+            if options.single_quote == Some(true) {
+                args.push("--single-quote".to_string());
+            }
+            args
+        }
+        OptionStyle::RustfmtConfig => {
+            // rustfmt has no single-quote concept; pass the rest as an inline
+            // config override rather than writing a transient rustfmt.toml.
+            let mut config_pairs = Vec::new();
+            if let Some(width) = options.tab_width {
+                config_pairs.push(format!("tab_spaces={}", width));
+            }
+            if let Some(width) = options.print_width {
+                config_pairs.push(format!("max_width={}", width));
+            }
+            if config_pairs.is_empty() {
+                Vec::new()
+            } else {
+                vec!["--config".to_string(), config_pairs.join(",")]
+            }
+        }
+        OptionStyle::None => Vec::new(),
+    }
+}
@@ -0,0 +1,322 @@
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Kills an entire process group on drop, not just the immediate child.
+///
+/// `kill_on_drop(true)` on `tokio::process::Command` only signals the direct
+/// child — tools like `npx` fork a grandchild (e.g. node) that survives the
+/// parent's death and keeps running. Pairing `process_group(0)` at spawn time
+/// (new pgid == child pid) with this guard means dropping the in-flight
+/// future on a `tokio::time::timeout` cancellation kills the whole tree.
+#[cfg(unix)]
+struct ProcessGroupGuard(i32);
+
+#[cfg(unix)]
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        unsafe {
+            libc::kill(-self.0, libc::SIGKILL);
+        }
+    }
+}
+
+/// Put `cmd`'s child in a new process group so [`ProcessGroupGuard`] can kill
+/// its whole tree; a no-op on non-Unix targets.
+fn isolate_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+}
+
+/// Build a guard that SIGKILLs `child`'s process group when dropped, if
+/// `isolate_process_group` was used to spawn it. A no-op on non-Unix targets,
+/// where `kill_on_drop` remains the only line of defense.
+#[cfg(unix)]
+fn process_group_guard(child: &tokio::process::Child) -> Option<ProcessGroupGuard> {
+    child.id().map(|pid| ProcessGroupGuard(pid as i32))
+}
+
+#[cfg(not(unix))]
+fn process_group_guard(_child: &tokio::process::Child) -> Option<()> {
+    None
+}
+
+/// Removes a temp directory (recursively) on drop.
+///
+/// Cleanup used to run as a plain line after `spawn_and_collect(...).await`
+/// returned, but `tokio::time::timeout` cancels by dropping the in-flight
+/// future, so a timeout skipped straight past that line and leaked the
+/// directory on disk. Holding this guard across the await means the drop
+/// path cleans up too; `Drop::drop` can't be async, so this uses the
+/// synchronous `std::fs` removal rather than `tokio::fs`.
+struct TempDirGuard(std::path::PathBuf);
+
+impl Drop for TempDirGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Path the registry is loaded from at startup, relative to the working
+/// directory. [`FormatterRegistry::load`] accepts any path, but `main` always
+/// passes this one; there is no flag or env var to change it.
+pub const DEFAULT_CONFIG_PATH: &str = "formatters.toml";
+
+/// How source code is handed to a formatter's child process.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum InputMode {
+    /// Write the code to the child's stdin and read the formatted result from stdout.
+    Stdin,
+    /// Write the code to a temp file named from `filename_template` (so tools
+    /// that dispatch on extension, like `gofmt`/`clang-format`, see the right
+    /// language) and read the formatted result from stdout.
+    TempFile { filename_template: String },
+}
+
+/// Which CLI-argument convention a formatter's `options` map translates to, if
+/// any. Declared per registry entry (rather than keyed off `name`) so renaming
+/// an entry, or registering a second tool that speaks the same convention,
+/// doesn't silently stop `tab_width`/`print_width`/`single_quote` from reaching
+/// the formatter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionStyle {
+    /// `--tab-width`/`--print-width`/`--single-quote`, e.g. prettier.
+    Prettier,
+    /// A single `--config tab_spaces=N,max_width=N` override, e.g. rustfmt.
+    RustfmtConfig,
+    /// No declared option support; validated options are silently dropped
+    /// rather than passed through.
+    #[default]
+    None,
+}
+
+/// One registered formatting tool: how to invoke it and which languages it serves.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FormatterSpec {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub option_style: OptionStyle,
+    #[serde(flatten)]
+    pub input_mode: InputMode,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormatterConfigFile {
+    #[serde(default)]
+    formatters: Vec<FormatterSpec>,
+}
+
+/// Declarative registry of formatting tools, loaded from a TOML config file at
+/// startup. Falls back to the built-in prettier/rustfmt wiring when no config
+/// file is present, so the service works unconfigured.
+pub struct FormatterRegistry {
+    formatters: Vec<FormatterSpec>,
+}
+
+impl FormatterRegistry {
+    /// Load the registry from `path`, falling back to [`FormatterRegistry::builtin`]
+    /// if the file doesn't exist or fails to parse.
+    pub fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match toml::from_str::<FormatterConfigFile>(&contents) {
+                Ok(cfg) if !cfg.formatters.is_empty() => {
+                    info!("Loaded {} formatter(s) from {}", cfg.formatters.len(), path);
+                    Self { formatters: cfg.formatters }
+                }
+                Ok(_) => {
+                    warn!("{} has no [[formatters]] entries, using built-in defaults", path);
+                    Self::builtin()
+                }
+                Err(e) => {
+                    warn!("Failed to parse {}: {}, using built-in defaults", path, e);
+                    Self::builtin()
+                }
+            },
+            Err(_) => Self::builtin(),
+        }
+    }
+
+    /// The hardcoded prettier/rustfmt registrations this service shipped with
+    /// before `formatters.toml` support existed.
+    pub fn builtin() -> Self {
+        Self {
+            formatters: vec![
+                FormatterSpec {
+                    name: "prettier".to_string(),
+                    command: "npx".to_string(),
+                    args: vec![
+                        "prettier".to_string(),
+                        "--stdin-filepath".to_string(),
+                        "file.js".to_string(),
+                        "--parser".to_string(),
+                        "babel".to_string(),
+                    ],
+                    languages: vec![
+                        "javascript".to_string(),
+                        "typescript".to_string(),
+                        "js".to_string(),
+                        "ts".to_string(),
+                    ],
+                    option_style: OptionStyle::Prettier,
+                    input_mode: InputMode::Stdin,
+                },
+                FormatterSpec {
+                    name: "rustfmt".to_string(),
+                    command: "rustfmt".to_string(),
+                    args: vec!["--emit".to_string(), "stdout".to_string()],
+                    languages: vec!["rust".to_string()],
+                    option_style: OptionStyle::RustfmtConfig,
+                    input_mode: InputMode::Stdin,
+                },
+            ],
+        }
+    }
+
+    /// Find the formatter to use for `language`, optionally pinned to a specific
+    /// `formatter_name`. Returns `None` if no registered formatter matches.
+    pub fn find(&self, language: &str, formatter_name: Option<&str>) -> Option<&FormatterSpec> {
+        match formatter_name {
+            Some(name) => self
+                .formatters
+                .iter()
+                .find(|f| f.name == name && f.languages.iter().any(|l| l == language)),
+            None => self
+                .formatters
+                .iter()
+                .find(|f| f.languages.iter().any(|l| l == language)),
+        }
+    }
+
+    /// All registered formatters, e.g. for rendering the UI's language list.
+    pub fn all(&self) -> &[FormatterSpec] {
+        &self.formatters
+    }
+
+    pub fn is_known_formatter(&self, formatter_name: &str) -> bool {
+        self.formatters.iter().any(|f| f.name == formatter_name)
+    }
+}
+
+/// Run `spec` against `code`, returning the formatted output. `extra_args`
+/// (e.g. translated from request-level formatting options) are appended
+/// after the spec's configured args.
+pub async fn run_formatter(
+    spec: &FormatterSpec,
+    code: &str,
+    extra_args: &[String],
+) -> Result<String, String> {
+    match &spec.input_mode {
+        InputMode::Stdin => run_via_stdin(spec, code, extra_args).await,
+        InputMode::TempFile { filename_template } => {
+            run_via_temp_file(spec, code, filename_template, extra_args).await
+        }
+    }
+}
+
+async fn spawn_and_collect(mut cmd: Command, formatter_name: &str) -> Result<std::process::Output, String> {
+    isolate_process_group(&mut cmd);
+    let child = cmd
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {}", formatter_name, e))?;
+    let _group_guard = process_group_guard(&child);
+
+    child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("{} failed: {}", formatter_name, e))
+}
+
+async fn run_via_stdin(
+    spec: &FormatterSpec,
+    code: &str,
+    extra_args: &[String],
+) -> Result<String, String> {
+    let mut cmd = Command::new(&spec.command);
+    cmd.args(&spec.args)
+        .args(extra_args)
+        .stdin(std::process::Stdio::piped());
+    isolate_process_group(&mut cmd);
+
+    let mut child = cmd
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {}", spec.name, e))?;
+    let _group_guard = process_group_guard(&child);
+
+    let mut stdin = child.stdin.take().ok_or("Failed to open stdin")?;
+    stdin
+        .write_all(code.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("{} failed: {}", spec.name, e))?;
+
+    finish(spec, output)
+}
+
+async fn run_via_temp_file(
+    spec: &FormatterSpec,
+    code: &str,
+    filename_template: &str,
+    extra_args: &[String],
+) -> Result<String, String> {
+    let dir = std::env::temp_dir().join(format!("speed-formatter-{}", uuid_like()));
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+    let _dir_guard = TempDirGuard(dir.clone());
+    let file_path = dir.join(filename_template);
+    tokio::fs::write(&file_path, code)
+        .await
+        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+    let args: Vec<String> = spec
+        .args
+        .iter()
+        .map(|arg| arg.replace("{file}", &file_path.to_string_lossy()))
+        .chain(extra_args.iter().cloned())
+        .collect();
+
+    let mut cmd = Command::new(&spec.command);
+    cmd.args(&args);
+    let output = spawn_and_collect(cmd, &spec.name).await;
+
+    finish(spec, output?)
+}
+
+fn finish(spec: &FormatterSpec, output: std::process::Output) -> Result<String, String> {
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        let error = String::from_utf8_lossy(&output.stderr);
+        Err(format!("{} error: {}", spec.name, error))
+    }
+}
+
+/// Small dependency-free unique-ish suffix for temp directories; not a real UUID.
+fn uuid_like() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!(
+        "{}-{}",
+        std::process::id(),
+        n
+    )
+}
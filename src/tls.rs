@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::ServerConfig as RustlsServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:3000";
+
+/// Paths to a PEM certificate chain and private key, enabling TLS termination.
+pub struct TlsFiles {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+/// Resolved listen address plus optional TLS material.
+pub struct ServerSettings {
+    pub listen_addr: String,
+    pub tls: Option<TlsFiles>,
+}
+
+/// Parse `--listen`, `--tls-cert`, `--tls-key` CLI flags, falling back to the
+/// `LISTEN_ADDR`/`TLS_CERT`/`TLS_KEY` env vars, then defaults. TLS is only
+/// enabled when both a cert and key are resolved; otherwise the service
+/// falls back to plaintext, as before.
+pub fn parse_settings() -> ServerSettings {
+    let mut listen_addr =
+        std::env::var("LISTEN_ADDR").unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_string());
+    let mut cert_path = std::env::var("TLS_CERT").ok();
+    let mut key_path = std::env::var("TLS_KEY").ok();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => {
+                if let Some(value) = args.next() {
+                    listen_addr = value;
+                }
+            }
+            "--tls-cert" => {
+                if let Some(value) = args.next() {
+                    cert_path = Some(value);
+                }
+            }
+            "--tls-key" => {
+                if let Some(value) = args.next() {
+                    key_path = Some(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let tls = match (cert_path, key_path) {
+        (Some(cert_path), Some(key_path)) => Some(TlsFiles { cert_path, key_path }),
+        _ => None,
+    };
+
+    ServerSettings { listen_addr, tls }
+}
+
+/// Build a `TlsAcceptor` from the PEM certificate chain and private key at
+/// `tls.cert_path`/`tls.key_path`.
+pub fn load_acceptor(tls: &TlsFiles) -> std::io::Result<TlsAcceptor> {
+    let mut cert_reader = BufReader::new(File::open(&tls.cert_path)?);
+    let mut key_reader = BufReader::new(File::open(&tls.key_path)?);
+
+    let cert_chain: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut cert_reader)
+        .collect::<Result<_, _>>()?;
+
+    // `private_key` auto-detects PKCS1 (`BEGIN RSA PRIVATE KEY`), PKCS8
+    // (`BEGIN PRIVATE KEY`), and SEC1/EC (`BEGIN EC PRIVATE KEY`) encodings,
+    // unlike `pkcs8_private_keys` which only accepts the PKCS8 form.
+    let key = rustls_pemfile::private_key(&mut key_reader)?.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "no private key found")
+    })?;
+
+    let config = RustlsServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}